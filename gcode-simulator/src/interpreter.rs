@@ -0,0 +1,208 @@
+use crate::state::{CoordinateMode, State, Units};
+use gcode::{Gcode, Mnemonic, Span};
+use uom::si::f32::{Length, Velocity};
+
+/// A point on the gantry's bed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Point {
+    pub x: Length,
+    pub y: Length,
+}
+
+/// A single straight-line motion segment.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Move {
+    /// Where the move started.
+    pub from: Point,
+    /// Where the move ends up.
+    pub to: Point,
+    /// How fast the move is travelled at.
+    pub feed_rate: Velocity,
+    /// Is this a rapid positioning move (`G0`) or a regular feed move (`G1`)?
+    pub rapid: bool,
+    /// The location of the `Gcode` this move was generated from.
+    pub span: Span,
+}
+
+/// Consumes a stream of [`Gcode`] commands, threading a [`State`] through
+/// them and yielding a [`Move`] for every `G0`/`G1` command encountered.
+///
+/// Everything else (`G20`/`G21`, `G90`/`G91`, bare `F` words, ...) updates
+/// the interpreter's `State` without producing a `Move`.
+#[derive(Debug, Clone)]
+pub struct Interpreter<I> {
+    state: State,
+    commands: I,
+}
+
+impl<I> Interpreter<I>
+where
+    I: Iterator<Item = Gcode>,
+{
+    /// Create a new `Interpreter` starting from the default `State`.
+    pub fn new(commands: I) -> Self {
+        Interpreter::with_state(commands, State::default())
+    }
+
+    /// Create a new `Interpreter` starting from a particular `State`.
+    pub fn with_state(commands: I, state: State) -> Self {
+        Interpreter { state, commands }
+    }
+
+    /// The interpreter's current state.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    fn apply(&mut self, gcode: &Gcode) -> Option<Move> {
+        if let Some(feed_rate) = gcode.value_for('F') {
+            self.state.feed_rate = self.state.to_speed(feed_rate);
+        }
+
+        if gcode.mnemonic() != Mnemonic::General {
+            return None;
+        }
+
+        match gcode.major_number() {
+            20 => {
+                self.state.units = Units::Imperial;
+                None
+            },
+            21 => {
+                self.state.units = Units::Metric;
+                None
+            },
+            90 => {
+                self.state.coordinate_mode = CoordinateMode::Absolute;
+                None
+            },
+            91 => {
+                self.state.coordinate_mode = CoordinateMode::Relative;
+                None
+            },
+            0 => Some(self.move_to(gcode, true)),
+            1 => Some(self.move_to(gcode, false)),
+            _ => None,
+        }
+    }
+
+    fn move_to(&mut self, gcode: &Gcode, rapid: bool) -> Move {
+        let from = Point {
+            x: self.state.x,
+            y: self.state.y,
+        };
+
+        self.state.x = self.resolve(self.state.x, gcode.value_for('X'));
+        self.state.y = self.resolve(self.state.y, gcode.value_for('Y'));
+
+        Move {
+            from,
+            to: Point {
+                x: self.state.x,
+                y: self.state.y,
+            },
+            feed_rate: self.state.feed_rate,
+            rapid,
+            span: gcode.span(),
+        }
+    }
+
+    fn resolve(&self, current: Length, value: Option<f32>) -> Length {
+        match (self.state.coordinate_mode, value) {
+            (_, None) => current,
+            (CoordinateMode::Absolute, Some(value)) => self.state.to_length(value),
+            (CoordinateMode::Relative, Some(value)) => {
+                current + self.state.to_length(value)
+            },
+        }
+    }
+}
+
+impl<I> Iterator for Interpreter<I>
+where
+    I: Iterator<Item = Gcode>,
+{
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        while let Some(gcode) = self.commands.next() {
+            if let Some(mv) = self.apply(&gcode) {
+                return Some(mv);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gcode::Word;
+    use uom::si::length::{inch, millimeter};
+
+    fn general(major_number: u32) -> Gcode {
+        Gcode::new(Mnemonic::General, major_number, Span::default())
+    }
+
+    #[test]
+    fn relative_moves_accumulate() {
+        let commands = vec![
+            general(91),
+            general(1).with_argument(Word::new('X', 5.0, Span::default())),
+            general(1).with_argument(Word::new('X', 5.0, Span::default())),
+        ];
+        let mut interpreter = Interpreter::new(commands.into_iter());
+
+        let first = interpreter.next().unwrap();
+        assert_eq!(first.from.x, Length::new::<millimeter>(0.0));
+        assert_eq!(first.to.x, Length::new::<millimeter>(5.0));
+
+        let second = interpreter.next().unwrap();
+        assert_eq!(second.from.x, Length::new::<millimeter>(5.0));
+        assert_eq!(second.to.x, Length::new::<millimeter>(10.0));
+
+        assert!(interpreter.next().is_none());
+    }
+
+    #[test]
+    fn units_can_be_switched_mid_stream() {
+        let commands = vec![
+            general(20),
+            general(1).with_argument(Word::new('X', 1.0, Span::default())),
+            general(21),
+            general(1).with_argument(Word::new('X', 25.0, Span::default())),
+        ];
+        let mut interpreter = Interpreter::new(commands.into_iter());
+
+        let imperial_move = interpreter.next().unwrap();
+        assert_eq!(imperial_move.to.x, Length::new::<inch>(1.0));
+
+        let metric_move = interpreter.next().unwrap();
+        assert_eq!(metric_move.to.x, Length::new::<millimeter>(25.0));
+
+        assert!(interpreter.next().is_none());
+    }
+
+    #[test]
+    fn feed_rate_updates_without_emitting_a_move() {
+        let dwell_with_feed_rate =
+            general(4).with_argument(Word::new('F', 300.0, Span::default()));
+        let mut interpreter = Interpreter::new(vec![dwell_with_feed_rate].into_iter());
+
+        assert!(interpreter.next().is_none());
+        assert_eq!(
+            interpreter.state().feed_rate,
+            State::default().to_speed(300.0)
+        );
+    }
+
+    #[test]
+    fn non_general_mnemonics_leave_state_untouched() {
+        let tool_change = Gcode::new(Mnemonic::ToolChange, 6, Span::default());
+        let mut interpreter = Interpreter::new(vec![tool_change].into_iter());
+
+        assert!(interpreter.next().is_none());
+        assert_eq!(interpreter.state(), State::default());
+    }
+}
@@ -0,0 +1,8 @@
+//! Turns a stream of [`gcode::Gcode`] commands into motion segments,
+//! threading a [`State`] through the program as it goes.
+
+mod interpreter;
+mod state;
+
+pub use crate::interpreter::{Interpreter, Move, Point};
+pub use crate::state::{CoordinateMode, State, Units};
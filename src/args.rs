@@ -0,0 +1,135 @@
+//! The storage backing [`Gcode`]'s argument list.
+//!
+//! By default this crate is `no_std` and stores arguments in a fixed-size
+//! [`ArrayVec`], capping a single `Gcode` at [`MAX_ARGS`] words. Enabling the
+//! `alloc` feature switches to a heap-backed [`Vec`] instead, so callers on a
+//! hosted target can parse programs with arbitrarily long argument lists.
+//!
+//! [`Gcode`]: ../struct.Gcode.html
+//! [`MAX_ARGS`]: ../constant.MAX_ARGS.html
+
+use crate::{Error, Word};
+
+#[cfg(not(feature = "alloc"))]
+mod backend {
+    use super::*;
+    use arrayvec::ArrayVec;
+    use crate::MAX_ARGS;
+
+    type Words = [Word; MAX_ARGS];
+
+    #[derive(Debug, Default, Clone, PartialEq)]
+    pub(crate) struct Arguments(ArrayVec<Words>);
+
+    impl Arguments {
+        pub(crate) fn new() -> Arguments {
+            Arguments(ArrayVec::default())
+        }
+
+        pub(crate) fn as_slice(&self) -> &[Word] {
+            &self.0
+        }
+
+        pub(crate) fn position(&self, letter: char) -> Option<usize> {
+            self.0.iter().position(|w| w.letter == letter)
+        }
+
+        pub(crate) fn set(&mut self, index: usize, arg: Word) {
+            self.0[index] = arg;
+        }
+
+        pub(crate) fn try_push(&mut self, arg: Word) -> Result<(), Error> {
+            let span = arg.span;
+            self.0
+                .try_push(arg)
+                .map_err(|_| Error::TooManyArguments(span))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod backend {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[derive(Debug, Default, Clone, PartialEq)]
+    pub(crate) struct Arguments(Vec<Word>);
+
+    impl Arguments {
+        pub(crate) fn new() -> Arguments {
+            Arguments(Vec::new())
+        }
+
+        pub(crate) fn as_slice(&self) -> &[Word] {
+            &self.0
+        }
+
+        pub(crate) fn position(&self, letter: char) -> Option<usize> {
+            self.0.iter().position(|w| w.letter == letter)
+        }
+
+        pub(crate) fn set(&mut self, index: usize, arg: Word) {
+            self.0[index] = arg;
+        }
+
+        pub(crate) fn try_push(&mut self, arg: Word) -> Result<(), Error> {
+            // The `Vec`-backed storage is unbounded, so pushing never fails.
+            self.0.push(arg);
+            Ok(())
+        }
+    }
+}
+
+pub(crate) use self::backend::Arguments;
+
+#[cfg(test)]
+mod tests {
+    use crate::{Gcode, Mnemonic, Span, Word};
+
+    fn word(letter: char, value: f32) -> Word {
+        Word::new(letter, value, Span::default())
+    }
+
+    /// Exercised against whichever backend is active, since the dedup
+    /// logic lives in `Gcode::try_add_argument` rather than in
+    /// `Arguments` itself.
+    #[test]
+    fn dedup_by_letter_overwrites_the_previous_value() {
+        let mut gcode = Gcode::new(Mnemonic::General, 1, Span::default());
+        gcode.add_argument(word('X', 1.0));
+        gcode.add_argument(word('X', 2.0));
+
+        assert_eq!(gcode.args().len(), 1);
+        assert_eq!(gcode.value_for('X'), Some(2.0));
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[test]
+    fn pushing_past_max_args_reports_too_many_arguments() {
+        let mut gcode = Gcode::new(Mnemonic::General, 1, Span::default());
+
+        for i in 0..crate::MAX_ARGS {
+            let letter = (b'A' + i as u8) as char;
+            gcode.try_add_argument(word(letter, i as f32)).unwrap();
+        }
+
+        let overflow = gcode.try_add_argument(word('Z', 99.0));
+
+        assert!(overflow.is_err());
+        assert_eq!(gcode.args().len(), crate::MAX_ARGS);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn the_alloc_backend_accepts_more_than_max_args_arguments() {
+        let mut gcode = Gcode::new(Mnemonic::General, 1, Span::default());
+        let total = crate::MAX_ARGS * 2;
+
+        for i in 0..total {
+            let letter = (b'A' + i as u8) as char;
+            gcode.try_add_argument(word(letter, i as f32)).unwrap();
+        }
+
+        assert_eq!(gcode.args().len(), total);
+    }
+}
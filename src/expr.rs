@@ -0,0 +1,302 @@
+//! A small recursive-descent parser and evaluator for the arithmetic
+//! expressions allowed inside a bracketed [`Value::Expression`]
+//! (e.g. `[#1+2.5]`).
+//!
+//! [`Value::Expression`]: ../enum.Value.html#variant.Expression
+
+use crate::{Error, Span};
+use alloc::boxed::Box;
+use core::iter::Peekable;
+use core::str::CharIndices;
+
+/// An arithmetic expression, as used inside a bracketed word value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A plain numeric literal.
+    Literal(f32),
+    /// A reference to a numbered parameter (e.g. `#5`).
+    Parameter(u32),
+    /// A binary operation applied to two sub-expressions.
+    BinaryOp(Op, Box<Expr>, Box<Expr>),
+}
+
+/// A binary arithmetic operator.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl Expr {
+    /// Parse an expression from its textual form (without the surrounding
+    /// `[...]` or `(...)` brackets).
+    ///
+    /// Byte offsets in any returned [`Error`] are relative to the start of
+    /// `src`.
+    pub fn parse(src: &str) -> Result<Expr, Error> {
+        let mut parser = Parser {
+            src,
+            chars: src.char_indices().peekable(),
+        };
+        let expr = parser.expression()?;
+
+        if let Some((offset, _)) = parser.peek() {
+            return Err(Error::InvalidExpression(parser.span_at(offset)));
+        }
+
+        Ok(expr)
+    }
+
+    /// Evaluate the expression, resolving any parameter references with the
+    /// provided lookup function.
+    pub fn evaluate<F>(&self, lookup: &F) -> Option<f32>
+    where
+        F: Fn(u32) -> Option<f32>,
+    {
+        match *self {
+            Expr::Literal(value) => Some(value),
+            Expr::Parameter(number) => lookup(number),
+            Expr::BinaryOp(op, ref lhs, ref rhs) => {
+                let lhs = lhs.evaluate(lookup)?;
+                let rhs = rhs.evaluate(lookup)?;
+
+                Some(match op {
+                    Op::Add => lhs + rhs,
+                    Op::Subtract => lhs - rhs,
+                    Op::Multiply => lhs * rhs,
+                    Op::Divide => lhs / rhs,
+                })
+            },
+        }
+    }
+}
+
+impl ::core::fmt::Display for Expr {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        match *self {
+            Expr::Literal(value) => write!(f, "{}", value),
+            Expr::Parameter(number) => write!(f, "#{}", number),
+            Expr::BinaryOp(op, ref lhs, ref rhs) => {
+                let op = match op {
+                    Op::Add => '+',
+                    Op::Subtract => '-',
+                    Op::Multiply => '*',
+                    Op::Divide => '/',
+                };
+                write!(f, "{}{}{}", lhs, op, rhs)
+            },
+        }
+    }
+}
+
+/// `expression := term (('+' | '-') term)*`
+/// `term       := factor (('*' | '/') factor)*`
+/// `factor     := number | '#' number | '(' expression ')' | '[' expression ']'`
+struct Parser<'input> {
+    src: &'input str,
+    chars: Peekable<CharIndices<'input>>,
+}
+
+impl<'input> Parser<'input> {
+    fn peek(&mut self) -> Option<(usize, char)> {
+        self.chars.peek().copied()
+    }
+
+    fn current_offset(&mut self) -> usize {
+        self.peek().map(|(i, _)| i).unwrap_or_else(|| self.src.len())
+    }
+
+    fn span_at(&self, offset: usize) -> Span {
+        Span::new(offset, offset + 1, 0)
+    }
+
+    fn expression(&mut self) -> Result<Expr, Error> {
+        let mut lhs = self.term()?;
+
+        loop {
+            match self.peek().map(|(_, c)| c) {
+                Some('+') => {
+                    self.chars.next();
+                    let rhs = self.term()?;
+                    lhs = Expr::BinaryOp(Op::Add, Box::new(lhs), Box::new(rhs));
+                },
+                Some('-') => {
+                    self.chars.next();
+                    let rhs = self.term()?;
+                    lhs = Expr::BinaryOp(
+                        Op::Subtract,
+                        Box::new(lhs),
+                        Box::new(rhs),
+                    );
+                },
+                _ => break,
+            }
+        }
+
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, Error> {
+        let mut lhs = self.factor()?;
+
+        loop {
+            match self.peek().map(|(_, c)| c) {
+                Some('*') => {
+                    self.chars.next();
+                    let rhs = self.factor()?;
+                    lhs = Expr::BinaryOp(
+                        Op::Multiply,
+                        Box::new(lhs),
+                        Box::new(rhs),
+                    );
+                },
+                Some('/') => {
+                    self.chars.next();
+                    let rhs = self.factor()?;
+                    lhs = Expr::BinaryOp(
+                        Op::Divide,
+                        Box::new(lhs),
+                        Box::new(rhs),
+                    );
+                },
+                _ => break,
+            }
+        }
+
+        Ok(lhs)
+    }
+
+    fn factor(&mut self) -> Result<Expr, Error> {
+        match self.peek() {
+            Some((_, open @ '(')) | Some((_, open @ '[')) => {
+                let close = if open == '(' { ')' } else { ']' };
+                self.chars.next();
+                let expr = self.expression()?;
+
+                match self.chars.next() {
+                    Some((_, c)) if c == close => Ok(expr),
+                    Some((offset, _)) => {
+                        Err(Error::InvalidExpression(self.span_at(offset)))
+                    },
+                    None => {
+                        let offset = self.current_offset();
+                        Err(Error::InvalidExpression(self.span_at(offset)))
+                    },
+                }
+            },
+            Some((_, '#')) => {
+                self.chars.next();
+                let number = self.number()?;
+                Ok(Expr::Parameter(number as u32))
+            },
+            Some((_, c)) if c.is_ascii_digit() || c == '-' || c == '.' => {
+                Ok(Expr::Literal(self.number()?))
+            },
+            Some((offset, _)) => {
+                Err(Error::InvalidExpression(self.span_at(offset)))
+            },
+            None => {
+                let offset = self.current_offset();
+                Err(Error::InvalidExpression(self.span_at(offset)))
+            },
+        }
+    }
+
+    fn number(&mut self) -> Result<f32, Error> {
+        let start = self.current_offset();
+
+        if let Some((_, '-')) = self.peek() {
+            self.chars.next();
+        }
+
+        let digits_start = self.current_offset();
+        while let Some((_, c)) = self.peek() {
+            if c.is_ascii_digit() || c == '.' {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        let end = self.current_offset();
+
+        if end == digits_start {
+            return Err(Error::InvalidExpression(self.span_at(start)));
+        }
+
+        self.src[start..end]
+            .parse()
+            .map_err(|_| Error::InvalidExpression(Span::new(start, end, 0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_params(_: u32) -> Option<f32> {
+        None
+    }
+
+    #[test]
+    fn operator_precedence() {
+        let expr = Expr::parse("1+2*3").unwrap();
+
+        assert_eq!(expr.evaluate(&no_params), Some(7.0));
+    }
+
+    #[test]
+    fn nested_brackets() {
+        let expr = Expr::parse("[1+2]*[3-1]").unwrap();
+
+        assert_eq!(expr.evaluate(&no_params), Some(6.0));
+    }
+
+    #[test]
+    fn mismatched_brackets_are_rejected() {
+        assert!(Expr::parse("[1+2)").is_err());
+        assert!(Expr::parse("(1+2]").is_err());
+    }
+
+    #[test]
+    fn parameter_references_are_resolved_via_the_lookup_closure() {
+        let expr = Expr::parse("#5+1").unwrap();
+
+        let lookup = |n: u32| if n == 5 { Some(41.0) } else { None };
+
+        assert_eq!(expr.evaluate(&lookup), Some(42.0));
+    }
+
+    #[test]
+    fn unresolvable_parameter_fails_to_evaluate() {
+        let expr = Expr::parse("#5").unwrap();
+
+        assert_eq!(expr.evaluate(&no_params), None);
+    }
+
+    #[test]
+    fn division() {
+        let expr = Expr::parse("10/4").unwrap();
+
+        assert_eq!(expr.evaluate(&no_params), Some(2.5));
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        assert!(Expr::parse("1+").is_err());
+        assert!(Expr::parse("*1").is_err());
+        assert!(Expr::parse("[1+2").is_err());
+        assert!(Expr::parse("1+2)").is_err());
+    }
+
+    #[test]
+    fn errors_carry_a_real_byte_span() {
+        let err = Expr::parse("1+2)").unwrap_err();
+
+        match err {
+            Error::InvalidExpression(span) => assert_eq!(span.start, 3),
+            other => panic!("expected InvalidExpression, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! A parser for the g-code programming language.
+
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+mod args;
+#[cfg(feature = "alloc")]
+mod expr;
+mod parser;
+mod types;
+
+pub use crate::parser::{parse, parse_with_callback, Parser};
+pub use crate::types::*;
@@ -0,0 +1,462 @@
+//! Turns g-code source text into a stream of [`Gcode`] commands.
+//!
+//! Parsing never stops at the first error: the offending command yields an
+//! `Err` and the [`Parser`] resumes with whatever comes next, so a consumer
+//! can surface every diagnostic tied to its exact byte [`Span`] rather than
+//! bailing out on the first mistake.
+
+use crate::{Error, Gcode, Mnemonic, Span, Value, Word};
+use core::convert::TryFrom;
+use core::iter::Peekable;
+use core::str::CharIndices;
+
+#[cfg(feature = "alloc")]
+use crate::expr::Expr;
+
+/// Parse a string of g-code into an iterator of `Result<Gcode, Error>`.
+pub fn parse(src: &str) -> Parser<'_> {
+    Parser {
+        src,
+        chars: src.char_indices().peekable(),
+        line: 0,
+        pending_line_number: None,
+    }
+}
+
+/// Parse a string of g-code, handing every [`Error`] to `callback` and
+/// yielding only the commands that parsed successfully.
+pub fn parse_with_callback<'input, F>(
+    src: &'input str,
+    mut callback: F,
+) -> impl Iterator<Item = Gcode> + 'input
+where
+    F: FnMut(Error) + 'input,
+{
+    parse(src).filter_map(move |result| match result {
+        Ok(gcode) => Some(gcode),
+        Err(error) => {
+            callback(error);
+            None
+        },
+    })
+}
+
+/// An iterator which lexes and parses g-code source text one [`Gcode`] at a
+/// time.
+pub struct Parser<'input> {
+    src: &'input str,
+    chars: Peekable<CharIndices<'input>>,
+    line: usize,
+    pending_line_number: Option<(u32, Span)>,
+}
+
+impl<'input> Parser<'input> {
+    fn peek_char(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn current_offset(&mut self) -> usize {
+        self.chars.peek().map(|&(i, _)| i).unwrap_or(self.src.len())
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            match self.peek_char() {
+                Some('\n') => {
+                    self.line += 1;
+                    self.chars.next();
+                },
+                Some(c) if c.is_whitespace() => {
+                    self.chars.next();
+                },
+                Some(';') => {
+                    while let Some(c) = self.peek_char() {
+                        if c == '\n' {
+                            break;
+                        }
+                        self.chars.next();
+                    }
+                },
+                Some('(') => {
+                    while let Some(c) = self.peek_char() {
+                        self.chars.next();
+                        if c == ')' {
+                            break;
+                        }
+                    }
+                },
+                _ => break,
+            }
+        }
+    }
+
+    fn take_while<F>(&mut self, mut predicate: F) -> (usize, usize)
+    where
+        F: FnMut(char) -> bool,
+    {
+        let start = self.current_offset();
+
+        while let Some(c) = self.peek_char() {
+            if predicate(c) {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+
+        (start, self.current_offset())
+    }
+
+    fn integer(&mut self) -> Result<u32, Error> {
+        let (start, end) = self.take_while(|c| c.is_ascii_digit());
+
+        if start == end {
+            return Err(Error::NumberOutOfRange(Span::new(
+                start, end, self.line,
+            )));
+        }
+
+        self.src[start..end]
+            .parse()
+            .map_err(|_| Error::NumberOutOfRange(Span::new(start, end, self.line)))
+    }
+
+    fn major_minor(&mut self) -> Result<(u32, Option<u32>), Error> {
+        let major = self.integer()?;
+
+        if let Some('.') = self.peek_char() {
+            self.chars.next();
+            let start = self.current_offset();
+
+            let minor = match self.peek_char() {
+                Some(c) if c.is_ascii_digit() => {
+                    self.chars.next();
+                    c.to_digit(10).unwrap()
+                },
+                _ => {
+                    return Err(Error::NumberOutOfRange(Span::new(
+                        start, start, self.line,
+                    )));
+                },
+            };
+
+            // `Gcode::minor_number()` only has room for a single digit, so
+            // any further digits (e.g. the `2` in `G1.12`) are dropped.
+            self.take_while(|c| c.is_ascii_digit());
+
+            Ok((major, Some(minor)))
+        } else {
+            Ok((major, None))
+        }
+    }
+
+    fn literal_number(&mut self) -> Result<f32, Error> {
+        let start = self.current_offset();
+
+        if let Some('-') = self.peek_char() {
+            self.chars.next();
+        }
+        self.take_while(|c| c.is_ascii_digit());
+        if let Some('.') = self.peek_char() {
+            self.chars.next();
+            self.take_while(|c| c.is_ascii_digit());
+        }
+
+        let end = self.current_offset();
+        self.src[start..end]
+            .parse()
+            .map_err(|_| Error::NumberOutOfRange(Span::new(start, end, self.line)))
+    }
+
+    /// Parse the value of an argument word (e.g. the `12.3` in `X12.3`, the
+    /// `#5` in `X#5`, or the `[#1+2.5]` in `X[#1+2.5]`).
+    fn value(&mut self) -> Result<Value, Error> {
+        match self.peek_char() {
+            Some('#') => {
+                self.chars.next();
+                let (start, end) = self.take_while(|c| c.is_ascii_digit());
+
+                if start == end {
+                    return Err(Error::NumberOutOfRange(Span::new(
+                        start, end, self.line,
+                    )));
+                }
+
+                self.src[start..end].parse().map(Value::Parameter).map_err(
+                    |_| Error::NumberOutOfRange(Span::new(start, end, self.line)),
+                )
+            },
+            Some('[') => self.bracketed_expression(),
+            _ => self.literal_number().map(Value::Literal),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn bracketed_expression(&mut self) -> Result<Value, Error> {
+        let start = self.current_offset();
+        self.chars.next(); // consume the opening '['
+
+        let mut depth = 1;
+        let inner_start = self.current_offset();
+
+        loop {
+            match self.peek_char() {
+                Some('[') => {
+                    depth += 1;
+                    self.chars.next();
+                },
+                Some(']') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    self.chars.next();
+                },
+                Some(_) => {
+                    self.chars.next();
+                },
+                None => {
+                    let span = Span::new(start, self.current_offset(), self.line);
+                    return Err(Error::InvalidExpression(span));
+                },
+            }
+        }
+
+        let inner_end = self.current_offset();
+        self.chars.next(); // consume the closing ']'
+        let span = Span::new(start, self.current_offset(), self.line);
+
+        Expr::parse(&self.src[inner_start..inner_end])
+            .map(Value::Expression)
+            .map_err(|_| Error::InvalidExpression(span))
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn bracketed_expression(&mut self) -> Result<Value, Error> {
+        let start = self.current_offset();
+        self.chars.next();
+        Err(Error::InvalidExpression(Span::new(
+            start,
+            self.current_offset(),
+            self.line,
+        )))
+    }
+
+    /// Parse a single `Gcode`, starting right after its mnemonic letter has
+    /// already been consumed.
+    fn gcode(&mut self, mnemonic: Mnemonic, start: usize) -> Result<Gcode, Error> {
+        let (major, minor) = self.major_minor()?;
+        let span = Span::new(start, self.current_offset(), self.line);
+
+        let mut gcode = Gcode::new(mnemonic, major, span);
+        if let Some(minor) = minor {
+            gcode = gcode.with_minor_number(minor);
+        }
+        if let Some((number, line_span)) = self.pending_line_number.take() {
+            gcode = gcode.with_line_number(number, line_span);
+        }
+
+        loop {
+            self.skip_whitespace_and_comments();
+
+            let letter = match self.peek_char() {
+                Some(c) if c.is_ascii_alphabetic() => c,
+                _ => break,
+            };
+
+            // A mnemonic (or line number) letter starts the next command.
+            if letter.eq_ignore_ascii_case(&'n') || Mnemonic::try_from(letter).is_ok()
+            {
+                break;
+            }
+
+            let arg_start = self.current_offset();
+            self.chars.next();
+            let value = self.value()?;
+            let arg_span = Span::new(arg_start, self.current_offset(), self.line);
+
+            gcode.try_add_argument(Word::with_value(letter, value, arg_span))?;
+        }
+
+        Ok(gcode)
+    }
+}
+
+impl<'input> Iterator for Parser<'input> {
+    type Item = Result<Gcode, Error>;
+
+    fn next(&mut self) -> Option<Result<Gcode, Error>> {
+        loop {
+            self.skip_whitespace_and_comments();
+            let (start, letter) = *self.chars.peek()?;
+
+            if !letter.is_ascii_alphabetic() {
+                self.chars.next();
+                continue;
+            }
+
+            if letter.eq_ignore_ascii_case(&'n') {
+                self.chars.next();
+                match self.integer() {
+                    Ok(number) => {
+                        let span = Span::new(start, self.current_offset(), self.line);
+                        self.pending_line_number = Some((number, span));
+                        continue;
+                    },
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+
+            self.chars.next();
+
+            return Some(match Mnemonic::try_from(letter) {
+                Ok(mnemonic) => self.gcode(mnemonic, start),
+                Err(_) => {
+                    let span =
+                        Span::new(start, start + letter.len_utf8(), self.line);
+                    Err(Error::UnknownMnemonic(letter, span))
+                },
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+    use std::vec::Vec;
+
+    fn parse_one(src: &str) -> Gcode {
+        crate::parse(src).next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn unknown_mnemonic_is_reported() {
+        let mut results = crate::parse("Q12");
+
+        let err = results.next().unwrap().unwrap_err();
+        match err {
+            Error::UnknownMnemonic(letter, _) => assert_eq!(letter, 'Q'),
+            other => panic!("expected UnknownMnemonic, got {:?}", other),
+        }
+        assert!(results.next().is_none());
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[test]
+    fn too_many_arguments_is_reported() {
+        // MAX_ARGS is 8, so A..J (9 letters, none of them mnemonics)
+        // overflows the ArrayVec-backed storage used without `alloc`.
+        let src = "G1 A1 B2 C3 D4 E5 F6 H7 I8 J9";
+
+        let err = crate::parse(src).next().unwrap().unwrap_err();
+
+        match err {
+            Error::TooManyArguments(_) => {},
+            other => panic!("expected TooManyArguments, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn number_out_of_range_is_reported_from_real_source_text() {
+        let err = crate::parse("G1 X.").next().unwrap().unwrap_err();
+
+        match err {
+            Error::NumberOutOfRange(_) => {},
+            other => panic!("expected NumberOutOfRange, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn line_numbers_are_attached_to_the_next_command() {
+        let gcode = parse_one("N20 G1 X5");
+
+        assert_eq!(gcode.line_number(), Some(20));
+        assert_eq!(gcode.mnemonic(), Mnemonic::General);
+        assert_eq!(gcode.major_number(), 1);
+        assert_eq!(gcode.value_for('X'), Some(5.0));
+    }
+
+    #[test]
+    fn line_comments_are_skipped() {
+        let mut results = crate::parse("G1 X5 ; move over\nG1 Y6");
+
+        let first = results.next().unwrap().unwrap();
+        assert_eq!(first.value_for('X'), Some(5.0));
+        assert!(first.value_for('Y').is_none());
+
+        let second = results.next().unwrap().unwrap();
+        assert_eq!(second.value_for('Y'), Some(6.0));
+    }
+
+    #[test]
+    fn parenthesized_comments_are_skipped() {
+        let gcode = parse_one("G1 (move over) X5");
+
+        assert_eq!(gcode.value_for('X'), Some(5.0));
+    }
+
+    #[test]
+    fn a_stream_can_contain_multiple_commands() {
+        let gcodes: Vec<Gcode> = crate::parse("G0 X1\nG1 X2 Y3")
+            .map(|result| result.unwrap())
+            .collect();
+
+        assert_eq!(gcodes.len(), 2);
+        assert_eq!(gcodes[0].major_number(), 0);
+        assert_eq!(gcodes[0].value_for('X'), Some(1.0));
+        assert_eq!(gcodes[1].major_number(), 1);
+        assert_eq!(gcodes[1].value_for('X'), Some(2.0));
+        assert_eq!(gcodes[1].value_for('Y'), Some(3.0));
+    }
+
+    #[test]
+    fn parse_with_callback_only_yields_the_commands_that_parsed() {
+        // An unknown mnemonic letter is only ever reported when it starts a
+        // fresh command; placing it at the very front of the stream is the
+        // simplest way to trigger that path without it being swallowed as
+        // an argument of the command before it.
+        let mut errors = Vec::new();
+        let gcodes: Vec<Gcode> =
+            crate::parse_with_callback("Q9\nG1 X1\nG1 X2", |err| errors.push(err))
+                .collect();
+
+        assert_eq!(gcodes.len(), 2);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn parameter_words_are_lexed_through_value() {
+        let gcode = parse_one("G1 X#5");
+
+        match &gcode.args()[0].value {
+            Value::Parameter(n) => assert_eq!(*n, 5),
+            other => panic!("expected Value::Parameter, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn bracketed_expressions_are_lexed_and_parsed_through_value() {
+        let gcode = parse_one("G1 X[1+2*3]");
+
+        match &gcode.args()[0].value {
+            Value::Expression(expr) => {
+                assert_eq!(expr.evaluate(&|_: u32| None), Some(7.0));
+            },
+            other => panic!("expected Value::Expression, got {:?}", other),
+        }
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[test]
+    fn bracketed_expressions_are_rejected_without_the_alloc_feature() {
+        let err = crate::parse("G1 X[1+2]").next().unwrap().unwrap_err();
+
+        match err {
+            Error::InvalidExpression(_) => {},
+            other => panic!("expected InvalidExpression, got {:?}", other),
+        }
+    }
+}
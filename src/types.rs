@@ -1,34 +1,53 @@
-use arrayvec::ArrayVec;
 use core::cmp;
+use core::convert::TryFrom;
 use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+use crate::args::Arguments;
 
-/// The maximum number of arguments a `Gcode` can have.
+/// The maximum number of arguments a `Gcode` can have when the crate is
+/// built without the `alloc` feature.
 pub const MAX_ARGS: usize = 8;
-type Words = [Word; MAX_ARGS];
 
 /// A single command in the `gcode` programming language.
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Gcode {
     mnemonic: Mnemonic,
-    number: f32,
+    major_number: u32,
+    minor_number: Option<u32>,
     line_number: Option<u32>,
     // invariant 1: All arguments are uppercase
-    arguments: ArrayVec<Words>,
+    arguments: Arguments,
     span: Span,
 }
 
 impl Gcode {
     /// Create a new `Gcode`.
-    pub fn new(mnemonic: Mnemonic, number: f32, span: Span) -> Gcode {
+    pub fn new(mnemonic: Mnemonic, major_number: u32, span: Span) -> Gcode {
         Gcode {
             mnemonic,
-            number,
+            major_number,
+            minor_number: None,
             span,
-            arguments: ArrayVec::default(),
+            arguments: Arguments::new(),
             line_number: None,
         }
     }
 
+    /// Create a new `Gcode` with a minor number (e.g. the `1` in `G1.1`).
+    ///
+    /// # Panics (debug builds only)
+    ///
+    /// `minor_number` must be a single digit (`0..=9`), matching
+    /// [`minor_number()`]'s contract of being "the first digit after the
+    /// decimal point".
+    ///
+    /// [`minor_number()`]: #method.minor_number
+    pub fn with_minor_number(mut self, minor_number: u32) -> Self {
+        debug_assert!(minor_number <= 9);
+        self.minor_number = Some(minor_number);
+        self
+    }
+
     /// Get the `Mnemonic` used by this `Gcode`.
     pub fn mnemonic(&self) -> Mnemonic {
         self.mnemonic
@@ -41,7 +60,7 @@ impl Gcode {
 
     /// The arguments provided to the `Gcode`.
     pub fn args(&self) -> &[Word] {
-        &self.arguments
+        self.arguments.as_slice()
     }
 
     /// Get the line number given to this gode (e.g. the `20` in `N20 G04 P100`).
@@ -49,15 +68,24 @@ impl Gcode {
         self.line_number
     }
 
-    /// The number associated with this `Gcode` (e.g. the `01` in `G01 X123`).
+    /// The number associated with this `Gcode` (e.g. the `01` in `G01 X123`),
+    /// computed from the [`major_number()`] and [`minor_number()`] parts.
+    ///
+    /// [`major_number()`]: #method.major_number
+    /// [`minor_number()`]: #method.minor_number
     #[deprecated = "You probably want the `Gcode::major_number()` and `Gcode::minor_number()` methods instead"]
     pub fn number(&self) -> f32 {
-        self.number
+        match self.minor_number {
+            Some(minor) => {
+                self.major_number as f32 + minor as f32 / 10.0
+            },
+            None => self.major_number as f32,
+        }
     }
 
     /// The integral part of the `Gcode`'s number field.
     pub fn major_number(&self) -> u32 {
-        self.number.trunc() as u32
+        self.major_number
     }
 
     /// The first digit after the decimal point, if there was one.
@@ -67,30 +95,40 @@ impl Gcode {
     /// For all intents and purposes, a gcode like `G1.0` doesn't really have
     /// a minor number.
     pub fn minor_number(&self) -> Option<u32> {
-        let fraction = self.number.abs().fract();
-        let first_digit = (fraction / 0.1).round() as u32;
-
-        if first_digit == 0 {
-            None
-        } else {
-            Some(first_digit)
-        }
+        self.minor_number
     }
 
     fn merge_span(&mut self, span: Span) {
         self.span = self.span.merge(span);
     }
 
-    /// Add an argument to this `Gcode`'s argument list.
-    pub fn add_argument(&mut self, mut arg: Word) {
+    /// Add an argument to this `Gcode`'s argument list, silently ignoring it
+    /// if the argument list is already full.
+    ///
+    /// See [`try_add_argument()`] if you want to be notified when an
+    /// argument is dropped.
+    ///
+    /// [`try_add_argument()`]: #method.try_add_argument
+    pub fn add_argument(&mut self, arg: Word) {
+        let _ = self.try_add_argument(arg);
+    }
+
+    /// Add an argument to this `Gcode`'s argument list, returning
+    /// [`Error::TooManyArguments`] if there's no more room.
+    pub fn try_add_argument(&mut self, mut arg: Word) -> Result<(), Error> {
         self.merge_span(arg.span);
         arg.letter = arg.letter.to_ascii_uppercase();
-
-        match self.arguments.iter().position(|w| w.letter == arg.letter) {
-            Some(i) => self.arguments[i] = arg,
-            None => {
-                let _ = self.arguments.try_push(arg);
-            }
+        let span = arg.span;
+
+        match self.arguments.position(arg.letter) {
+            Some(i) => {
+                self.arguments.set(i, arg);
+                Ok(())
+            },
+            None => self
+                .arguments
+                .try_push(arg)
+                .map_err(|_| Error::TooManyArguments(span)),
         }
     }
 
@@ -109,14 +147,31 @@ impl Gcode {
         self
     }
 
-    /// Find the value for the desired argument.
+    /// Find the value for the desired argument, resolving it if it's a
+    /// plain numeric literal.
+    ///
+    /// Use [`Gcode::value_for_with()`] if the argument's value might be a
+    /// parameter reference or expression.
     pub fn value_for(&self, letter: char) -> Option<f32> {
+        self.word_for(letter)?.value.as_literal()
+    }
+
+    /// Find the value for the desired argument, resolving parameter
+    /// references and expressions with the provided lookup function.
+    pub fn value_for_with<F>(&self, letter: char, lookup: F) -> Option<f32>
+    where
+        F: Fn(u32) -> Option<f32>,
+    {
+        self.word_for(letter)?.value.resolve(&lookup)
+    }
+
+    fn word_for(&self, letter: char) -> Option<&Word> {
         let letter = letter.to_ascii_uppercase();
 
         self.arguments
+            .as_slice()
             .iter()
             .find(|word| letter == word.letter)
-            .map(|word| word.value)
     }
 }
 
@@ -127,7 +182,10 @@ impl Display for Gcode {
         }
 
         write!(f, "{}", self.mnemonic())?;
-        write!(f, "{}", self.number)?;
+        write!(f, "{}", self.major_number)?;
+        if let Some(minor) = self.minor_number {
+            write!(f, ".{}", minor)?;
+        }
 
         for arg in self.args() {
             write!(f, " {}", arg)?;
@@ -138,20 +196,24 @@ impl Display for Gcode {
 }
 
 /// A single `Word` in the `gcode` language (e.g. `X-12.3`).
-#[derive(Debug, Default, Copy, Clone, PartialEq)]
-#[repr(C)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Word {
     /// The letter associated with this word (e.g. the `X` in `X12.3`).
     pub letter: char,
-    /// The numeric part of the word.
-    pub value: f32,
+    /// The word's value (e.g. the `12.3` in `X12.3`, or the `#5` in `X#5`).
+    pub value: Value,
     /// The word's location in its original text.
     pub span: Span,
 }
 
 impl Word {
-    /// Create a new `Word`.
+    /// Create a new `Word` with a plain numeric literal.
     pub fn new(letter: char, value: f32, span: Span) -> Word {
+        Word::with_value(letter, Value::Literal(value), span)
+    }
+
+    /// Create a new `Word` with an arbitrary [`Value`].
+    pub fn with_value(letter: char, value: Value, span: Span) -> Word {
         Word {
             letter,
             value,
@@ -160,12 +222,73 @@ impl Word {
     }
 }
 
+impl Default for Word {
+    fn default() -> Word {
+        Word::new('\0', 0.0, Span::default())
+    }
+}
+
 impl Display for Word {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "{}{}", self.letter, self.value)
     }
 }
 
+/// The value associated with a [`Word`].
+///
+/// Most of the time this is just a plain numeric [`Value::Literal`], but
+/// some dialects (e.g. LinuxCNC-style controllers) also allow a word's value
+/// to reference a numbered parameter (`X#5`) or a bracketed arithmetic
+/// expression (`X[#1+2.5]`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A plain numeric literal (e.g. the `12.3` in `X12.3`).
+    Literal(f32),
+    /// A reference to a numbered parameter (e.g. the `5` in `X#5`).
+    Parameter(u32),
+    /// A bracketed arithmetic expression (e.g. `X[#1+2.5]`).
+    #[cfg(feature = "alloc")]
+    Expression(crate::expr::Expr),
+}
+
+impl Value {
+    /// Get this value's literal number, if it is one.
+    ///
+    /// `Parameter` and `Expression` values need a parameter lookup to
+    /// resolve to a number; see [`Value::resolve()`].
+    pub fn as_literal(&self) -> Option<f32> {
+        match *self {
+            Value::Literal(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Resolve this value to a number, looking up any referenced parameters
+    /// with the provided closure.
+    pub fn resolve<F>(&self, lookup: &F) -> Option<f32>
+    where
+        F: Fn(u32) -> Option<f32>,
+    {
+        match *self {
+            Value::Literal(value) => Some(value),
+            Value::Parameter(number) => lookup(number),
+            #[cfg(feature = "alloc")]
+            Value::Expression(ref expr) => expr.evaluate(lookup),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Value::Literal(value) => write!(f, "{}", value),
+            Value::Parameter(number) => write!(f, "#{}", number),
+            #[cfg(feature = "alloc")]
+            Value::Expression(ref expr) => write!(f, "[{}]", expr),
+        }
+    }
+}
+
 /// A general command category.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(C)]
@@ -199,6 +322,37 @@ impl Display for Mnemonic {
     }
 }
 
+impl FromStr for Mnemonic {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Mnemonic, Error> {
+        let mut chars = s.chars();
+        let letter = chars.next().ok_or_else(|| {
+            Error::UnknownMnemonic('\0', Span::default())
+        })?;
+
+        if chars.next().is_some() {
+            return Err(Error::UnknownMnemonic(letter, Span::default()));
+        }
+
+        Mnemonic::try_from(letter)
+    }
+}
+
+impl TryFrom<char> for Mnemonic {
+    type Error = Error;
+
+    fn try_from(letter: char) -> Result<Mnemonic, Error> {
+        match letter.to_ascii_uppercase() {
+            'O' => Ok(Mnemonic::ProgramNumber),
+            'T' => Ok(Mnemonic::ToolChange),
+            'M' => Ok(Mnemonic::MachineRoutine),
+            'G' => Ok(Mnemonic::General),
+            other => Err(Error::UnknownMnemonic(other, Span::default())),
+        }
+    }
+}
+
 /// A set of byte indices which correspond to the location of a substring in
 /// a larger piece of text.
 ///
@@ -250,13 +404,67 @@ impl Span {
     }
 }
 
+/// The errors that may be encountered while parsing a `Gcode` program.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Error {
+    /// The mnemonic letter (e.g. the `G` in `G01`) isn't one this crate
+    /// knows how to handle.
+    UnknownMnemonic(char, Span),
+    /// A numeric literal couldn't be represented by its target type.
+    NumberOutOfRange(Span),
+    /// More than [`MAX_ARGS`] arguments were given to a single `Gcode`.
+    ///
+    /// [`MAX_ARGS`]: constant.MAX_ARGS.html
+    TooManyArguments(Span),
+    /// A bracketed expression (e.g. `[#1+2.5]`) wasn't valid.
+    InvalidExpression(Span),
+}
+
+impl Error {
+    /// The [`Span`] that triggered this error.
+    pub fn span(&self) -> Span {
+        match *self {
+            Error::UnknownMnemonic(_, span) => span,
+            Error::NumberOutOfRange(span) => span,
+            Error::TooManyArguments(span) => span,
+            Error::InvalidExpression(span) => span,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownMnemonic(letter, _) => {
+                write!(f, "\"{}\" is not a known mnemonic", letter)
+            },
+            Error::NumberOutOfRange(_) => {
+                write!(f, "the number is out of range")
+            },
+            Error::TooManyArguments(_) => write!(
+                f,
+                "no more than {} arguments are allowed",
+                MAX_ARGS
+            ),
+            Error::InvalidExpression(_) => {
+                write!(f, "not a valid expression")
+            },
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::{format, vec};
 
     #[test]
     fn get_gcode_repr() {
-        let thing = Gcode::new(Mnemonic::General, 1.2, Span::default())
+        let thing = Gcode::new(Mnemonic::General, 1, Span::default())
+            .with_minor_number(2)
             .with_line_number(10, Span::default())
             .with_argument(Word::new('X', 500.0, Span::default()))
             .with_argument(Word::new('Y', -1.23, Span::default()));
@@ -268,14 +476,15 @@ mod tests {
 
     #[test]
     fn you_can_round_trip_a_gcode() {
-        let original = Gcode::new(Mnemonic::General, 1.2, Span::new(0, 20, 0))
+        let original = Gcode::new(Mnemonic::General, 1, Span::new(0, 20, 0))
+            .with_minor_number(2)
             .with_line_number(10, Span::default())
             .with_argument(Word::new('X', 500.0, Span::new(9, 13, 0)))
             .with_argument(Word::new('Y', -1.23, Span::new(14, 20, 0)));
 
         let serialized = format!("{}", original);
 
-        let got = ::parse(&serialized).next().unwrap();
+        let got = crate::parse(&serialized).next().unwrap().unwrap();
 
         assert_eq!(got, original);
     }
@@ -283,24 +492,25 @@ mod tests {
     #[test]
     fn major_and_minor_numbers_make_sense() {
         let inputs = vec![
-            (1.0, 1, None),
-            (1.1, 1, Some(1)),
-            (1.2, 1, Some(2)),
-            (1.3, 1, Some(3)),
-            (1.4, 1, Some(4)),
-            (1.5, 1, Some(5)),
-            (1.6, 1, Some(6)),
-            (1.7, 1, Some(7)),
-            (1.8, 1, Some(8)),
-            (1.9, 1, Some(9)),
-            (2.0, 2, None),
+            (1, None, 1.0),
+            (1, Some(1), 1.1),
+            (1, Some(2), 1.2),
+            (1, Some(3), 1.3),
+            (2, None, 2.0),
         ];
 
-        for (src, major, minor) in inputs {
-            let g = Gcode::new(Mnemonic::General, src, Span::default());
+        for (major, minor, number) in inputs {
+            let mut g = Gcode::new(Mnemonic::General, major, Span::default());
+            if let Some(minor) = minor {
+                g = g.with_minor_number(minor);
+            }
 
             assert_eq!(g.major_number(), major);
             assert_eq!(g.minor_number(), minor);
+            #[allow(deprecated)]
+            {
+                assert_eq!(g.number(), number);
+            }
         }
     }
 }